@@ -26,6 +26,51 @@ struct BackendState<'s> {
     config: Option<PathBuf>,
     workspace_folders: Vec<WorkspaceFolder>,
     router: Router<TyposCli<'s>>,
+    // in-memory copy of every open document, kept up to date by did_open/did_change/did_close
+    // so incremental did_change notifications have a buffer to apply their edits against
+    documents: HashMap<Url, String>,
+    position_encoding: PositionEncoding,
+    // address of the ConfigStorage currently backing router's TyposCli engines; see update_router
+    storage: Option<usize>,
+}
+
+// the position encoding negotiated with the client during initialize, used to interpret
+// and produce the `character` component of every LSP `Position` we handle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum PositionEncoding {
+    Utf8,
+    #[default]
+    Utf16,
+    Utf32,
+}
+
+impl PositionEncoding {
+    // server preference order, most desirable first: UTF-8 lets us skip the lossy
+    // `String::from_utf8_lossy` + re-count step entirely, so prefer it when offered
+    const PREFERENCE: [PositionEncoding; 3] = [
+        PositionEncoding::Utf8,
+        PositionEncoding::Utf16,
+        PositionEncoding::Utf32,
+    ];
+
+    // picks the first mutually supported encoding, falling back to UTF-16 (the LSP default)
+    // if the client didn't advertise `general.position_encodings` or none of them match
+    fn negotiate(offered: &[PositionEncodingKind]) -> Self {
+        Self::PREFERENCE
+            .into_iter()
+            .find(|preferred| offered.contains(&PositionEncodingKind::from(*preferred)))
+            .unwrap_or_default()
+    }
+}
+
+impl From<PositionEncoding> for PositionEncodingKind {
+    fn from(encoding: PositionEncoding) -> Self {
+        match encoding {
+            PositionEncoding::Utf8 => PositionEncodingKind::UTF8,
+            PositionEncoding::Utf16 => PositionEncodingKind::UTF16,
+            PositionEncoding::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
 }
 
 struct TyposCli<'s> {
@@ -35,12 +80,10 @@ struct TyposCli<'s> {
 
 // initialise an engine and overrides using the config file from path or its parent
 fn try_new_cli<'s>(
+    storage: &'s policy::ConfigStorage,
     path: &Path,
     config: Option<&Path>,
 ) -> anyhow::Result<TyposCli<'s>, anyhow::Error> {
-    // leak to get a 'static which is needed to satisfy the 's lifetime
-    // but does mean memory will grow unbounded
-    let storage = Box::leak(Box::new(policy::ConfigStorage::new()));
     let mut engine = typos_cli::policy::ConfigEngine::new(storage);
 
     // TODO: currently mimicking typos here but do we need to create and update
@@ -68,6 +111,40 @@ fn try_new_cli<'s>(
 }
 
 impl<'s> BackendState<'s> {
+    // applies the `diagnosticSeverity`/`config` settings, as sent via `initializationOptions`
+    // at startup or `workspace/didChangeConfiguration` afterwards
+    fn apply_settings(&mut self, settings: &serde_json::Value) {
+        let Some(values) = settings.as_object() else {
+            return;
+        };
+
+        if let Some(value) = values.get("diagnosticSeverity").cloned() {
+            match value.as_str().unwrap_or("").to_lowercase().as_str() {
+                "error" => {
+                    self.severity = Some(DiagnosticSeverity::ERROR);
+                }
+                "warning" => {
+                    self.severity = Some(DiagnosticSeverity::WARNING);
+                }
+                "information" => {
+                    self.severity = Some(DiagnosticSeverity::INFORMATION);
+                }
+                "hint" => {
+                    self.severity = Some(DiagnosticSeverity::HINT);
+                }
+                _ => {
+                    tracing::warn!("Unknown diagnostic severity: {}", value);
+                }
+            }
+        }
+        if let Some(value) = values.get("config").cloned() {
+            if let Some(value) = value.as_str() {
+                let expanded_path = PathBuf::from(shellexpand::tilde(value).to_string());
+                self.config = Some(expanded_path);
+            }
+        }
+    }
+
     fn set_workspace_folders(
         &mut self,
         workspace_folders: Vec<WorkspaceFolder>,
@@ -92,6 +169,18 @@ impl<'s> BackendState<'s> {
 
     fn update_router(&mut self) -> anyhow::Result<(), anyhow::Error> {
         self.router = Router::new();
+        // every TyposCli referencing the previous storage was just dropped above, so it's
+        // safe to reclaim it now rather than leaking again on each reload (ConfigStorage
+        // never invalidates a cached entry, so reusing it instead would serve stale configs)
+        if let Some(addr) = self.storage.take() {
+            // SAFETY: `addr` was produced by Box::leak below and nothing still references
+            // it, since self.router (the only thing we ever handed it to) was just replaced
+            unsafe { drop(Box::from_raw(addr as *mut policy::ConfigStorage)) };
+        }
+        let leaked: &'s policy::ConfigStorage = Box::leak(Box::new(policy::ConfigStorage::new()));
+        self.storage = Some(leaked as *const _ as usize);
+        let storage = leaked;
+
         for folder in self.workspace_folders.iter() {
             let path = folder
                 .uri
@@ -99,7 +188,7 @@ impl<'s> BackendState<'s> {
                 .map_err(|_| anyhow!("Cannot convert uri {} to file path", folder.uri))?;
             let route = format!("{}{}", url_path_sanitised(&folder.uri), "/*p");
             self.router
-                .insert_new_typos_cli(&route, &path, self.config.as_deref())?;
+                .insert_new_typos_cli(storage, &route, &path, self.config.as_deref())?;
         }
 
         // add low priority catch all route used for files outside the workspace, or
@@ -109,6 +198,7 @@ impl<'s> BackendState<'s> {
             // file:///c%3A/Users/oliver/typos-vscode/src/test/fixture
             let route = format!("/{}%3A/*p", &drive);
             self.router.insert_new_typos_cli(
+                storage,
                 &route,
                 &PathBuf::from(format!("{}:\\", &drive)),
                 self.config.as_deref(),
@@ -118,17 +208,22 @@ impl<'s> BackendState<'s> {
         #[cfg(not(windows))]
         {
             let route = "/*p";
-            self.router
-                .insert_new_typos_cli(route, &PathBuf::from("/"), self.config.as_deref())?;
+            self.router.insert_new_typos_cli(
+                storage,
+                route,
+                &PathBuf::from("/"),
+                self.config.as_deref(),
+            )?;
         }
 
         Ok(())
     }
 }
 
-trait RouterExt {
+trait RouterExt<'s> {
     fn insert_new_typos_cli(
         &mut self,
+        storage: &'s policy::ConfigStorage,
         route: &str,
         path: &Path,
         config: Option<&Path>,
@@ -136,22 +231,100 @@ trait RouterExt {
 }
 
 // TODO: extract
-impl RouterExt for Router<TyposCli<'_>> {
+impl<'s> RouterExt<'s> for Router<TyposCli<'s>> {
     // convenience method to insert a new TyposCli into the router
     // implemented as an extension trait to avoid interprocedural conflicts
     fn insert_new_typos_cli(
         &mut self,
+        storage: &'s policy::ConfigStorage,
         route: &str,
         path: &Path,
         config: Option<&Path>,
     ) -> anyhow::Result<(), anyhow::Error> {
         tracing::debug!("Adding route {} for path {}", route, path.display());
-        let cli = try_new_cli(path, config)?;
+        let cli = try_new_cli(storage, path, config)?;
         self.insert(route, cli)?;
         Ok(())
     }
 }
 
+// `file:` URIs carry a path we can route/check-overrides on; anything else (`untitled:`,
+// `vscode-notebook-cell:`, ...) is an in-memory buffer identified only by its scheme
+enum DocumentUri {
+    File(PathBuf),
+    InMemory { scheme: String },
+}
+
+impl DocumentUri {
+    fn new(uri: &Url) -> Self {
+        match uri.to_file_path() {
+            Ok(path) => DocumentUri::File(path),
+            Err(_) => DocumentUri::InMemory {
+                scheme: uri.scheme().to_string(),
+            },
+        }
+    }
+}
+
+// finds the typos.toml/_typos.toml that governs `path`, same as try_new_cli: the `config:`
+// override if set, otherwise the nearest one walking up, falling back to the workspace root
+fn resolve_config_path(
+    path: &Path,
+    workspace_folders: &[WorkspaceFolder],
+    config_override: Option<&Path>,
+) -> PathBuf {
+    if let Some(config_override) = config_override {
+        return config_override.to_path_buf();
+    }
+
+    for dir in path.ancestors() {
+        for name in ["typos.toml", "_typos.toml"] {
+            let candidate = dir.join(name);
+            if candidate.is_file() {
+                return candidate;
+            }
+        }
+    }
+
+    let root = workspace_folders
+        .iter()
+        .filter_map(|folder| folder.uri.to_file_path().ok())
+        .find(|root| path.starts_with(root))
+        .or_else(|| path.parent().map(Path::to_path_buf))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    root.join("typos.toml")
+}
+
+// adds `word` to `default.extend-words` as an identity mapping, e.g. `word = "word"`
+fn add_word_to_config(config_path: &Path, word: &str) -> anyhow::Result<()> {
+    let mut doc = if config_path.is_file() {
+        std::fs::read_to_string(config_path)?.parse::<toml_edit::Document>()?
+    } else {
+        toml_edit::Document::new()
+    };
+
+    let default_table = doc["default"].or_insert(toml_edit::table());
+    let default_table = default_table
+        .as_table_mut()
+        .ok_or_else(|| anyhow!("`default` in {} is not a table", config_path.display()))?;
+    let extend_words = default_table["extend-words"].or_insert(toml_edit::table());
+    let extend_words = extend_words.as_table_mut().ok_or_else(|| {
+        anyhow!(
+            "`default.extend-words` in {} is not a table",
+            config_path.display()
+        )
+    })?;
+    extend_words[word] = toml_edit::value(word);
+
+    if let Some(parent) = config_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(config_path, doc.to_string())?;
+
+    Ok(())
+}
+
 fn url_path_sanitised(url: &Url) -> String {
     // windows paths (eg: /C:/Users/..) may not be percent-encoded by some clients
     // and therefore contain colons, see
@@ -164,8 +337,13 @@ fn url_path_sanitised(url: &Url) -> String {
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 struct DiagnosticData<'c> {
     corrections: Vec<Cow<'c, str>>,
+    typo: Cow<'c, str>,
 }
 
+// name of the custom command registered with the client so an "add to dictionary" code
+// action can be actioned via workspace/executeCommand
+const ADD_WORD_TO_DICTIONARY_COMMAND: &str = "typos-lsp.addWordToDictionary";
+
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend<'static, 'static> {
     async fn initialize(&self, params: InitializeParams) -> jsonrpc::Result<InitializeResult> {
@@ -189,34 +367,16 @@ impl LanguageServer for Backend<'static, 'static> {
 
         let mut state = self.state.lock().unwrap();
 
+        state.position_encoding = params
+            .capabilities
+            .general
+            .as_ref()
+            .and_then(|general| general.position_encodings.as_deref())
+            .map(PositionEncoding::negotiate)
+            .unwrap_or_default();
+
         if let Some(ops) = params.initialization_options {
-            if let Some(values) = ops.as_object() {
-                if let Some(value) = values.get("diagnosticSeverity").cloned() {
-                    match value.as_str().unwrap_or("").to_lowercase().as_str() {
-                        "error" => {
-                            state.severity = Some(DiagnosticSeverity::ERROR);
-                        }
-                        "warning" => {
-                            state.severity = Some(DiagnosticSeverity::WARNING);
-                        }
-                        "information" => {
-                            state.severity = Some(DiagnosticSeverity::INFORMATION);
-                        }
-                        "hint" => {
-                            state.severity = Some(DiagnosticSeverity::HINT);
-                        }
-                        _ => {
-                            tracing::warn!("Unknown diagnostic severity: {}", value);
-                        }
-                    }
-                }
-                if let Some(value) = values.get("config").cloned() {
-                    if let Some(value) = value.as_str() {
-                        let expanded_path = PathBuf::from(shellexpand::tilde(value).to_string());
-                        state.config = Some(expanded_path);
-                    }
-                }
-            }
+            state.apply_settings(&ops);
         }
 
         if let Err(e) = state.set_workspace_folders(params.workspace_folders.unwrap_or_default()) {
@@ -225,11 +385,9 @@ impl LanguageServer for Backend<'static, 'static> {
 
         Ok(InitializeResult {
             capabilities: ServerCapabilities {
-                // only support UTF-16 positions for now, which is the default when unspecified
-                position_encoding: Some(PositionEncodingKind::UTF16),
+                position_encoding: Some(state.position_encoding.into()),
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    // TODO: should we support incremental?
-                    TextDocumentSyncKind::FULL,
+                    TextDocumentSyncKind::INCREMENTAL,
                 )),
                 code_action_provider: Some(CodeActionProviderCapability::Options(
                     CodeActionOptions {
@@ -247,6 +405,19 @@ impl LanguageServer for Backend<'static, 'static> {
                     }),
                     ..Default::default()
                 }),
+                diagnostic_provider: Some(DiagnosticServerCapabilities::Options(
+                    DiagnosticOptions {
+                        identifier: None,
+                        inter_file_dependencies: false,
+                        // backed by the workspace_diagnostic handler below
+                        workspace_diagnostics: true,
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    },
+                )),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: vec![ADD_WORD_TO_DICTIONARY_COMMAND.to_string()],
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
                 ..Default::default()
             },
             server_info: Some(ServerInfo {
@@ -260,19 +431,64 @@ impl LanguageServer for Backend<'static, 'static> {
         self.client
             .log_message(MessageType::INFO, "server initialized!")
             .await;
+
+        // watch typos.toml/_typos.toml so edits made outside the editor (or by another tool)
+        // trigger a config reload too
+        let watchers = ["**/typos.toml", "**/_typos.toml"]
+            .into_iter()
+            .map(|pattern| FileSystemWatcher {
+                glob_pattern: GlobPattern::String(pattern.to_string()),
+                kind: None,
+            })
+            .collect();
+
+        let registration = Registration {
+            id: "typos-lsp-config-watcher".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+                watchers,
+            })
+            .ok(),
+        };
+
+        if let Err(e) = self.client.register_capability(vec![registration]).await {
+            tracing::warn!(
+                "Failed to register for didChangeWatchedFiles notifications: {}",
+                e
+            );
+        }
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
         tracing::debug!("did_open: {:?}", to_string(&params).unwrap_or_default());
+        self.state.lock().unwrap().documents.insert(
+            params.text_document.uri.clone(),
+            params.text_document.text.clone(),
+        );
         self.report_diagnostics(params.text_document).await;
     }
 
-    async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
+    async fn did_change(&self, params: DidChangeTextDocumentParams) {
         tracing::debug!("did_change: {:?}", to_string(&params).unwrap_or_default());
+
+        let uri = params.text_document.uri;
+        let text = {
+            let mut state = self.state.lock().unwrap();
+            let encoding = state.position_encoding;
+            let buffer = state.documents.entry(uri.clone()).or_default();
+            // changes must be applied in order, each against the buffer as already
+            // edited by the previous change, so byte offsets are recomputed fresh
+            // for every change rather than cached
+            for change in params.content_changes {
+                apply_content_change(buffer, change, encoding);
+            }
+            buffer.clone()
+        };
+
         self.report_diagnostics(TextDocumentItem {
             language_id: "FOOBAR".to_string(),
-            uri: params.text_document.uri,
-            text: std::mem::take(&mut params.content_changes[0].text),
+            uri,
+            text,
             version: params.text_document.version,
         })
         .await;
@@ -285,6 +501,11 @@ impl LanguageServer for Backend<'static, 'static> {
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
         tracing::debug!("did_close: {:?}", to_string(&params).unwrap_or_default());
+        self.state
+            .lock()
+            .unwrap()
+            .documents
+            .remove(&params.text_document.uri);
         // clear diagnostics to avoid a stale diagnostics flash on open
         // if the file has typos fixed outside of vscode
         // see https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocument_publishDiagnostics
@@ -306,10 +527,10 @@ impl LanguageServer for Backend<'static, 'static> {
             .filter(|diag| diag.source == Some("typos".to_string()))
             .flat_map(|diag| match &diag.data {
                 Some(data) => {
-                    if let Ok(DiagnosticData { corrections }) =
+                    if let Ok(DiagnosticData { corrections, typo }) =
                         serde_json::from_value::<DiagnosticData>(data.clone())
                     {
-                        corrections
+                        let mut actions: Vec<CodeActionOrCommand> = corrections
                             .iter()
                             .map(|c| {
                                 CodeActionOrCommand::CodeAction(CodeAction {
@@ -334,7 +555,25 @@ impl LanguageServer for Backend<'static, 'static> {
                                     ..CodeAction::default()
                                 })
                             })
-                            .collect()
+                            .collect();
+
+                        let title = format!("Add `{}` to typos dictionary", typo);
+                        actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                            title: title.clone(),
+                            kind: Some(CodeActionKind::QUICKFIX),
+                            diagnostics: Some(vec![diag.clone()]),
+                            command: Some(Command {
+                                title,
+                                command: ADD_WORD_TO_DICTIONARY_COMMAND.to_string(),
+                                arguments: Some(vec![
+                                    json!(params.text_document.uri),
+                                    json!(typo),
+                                ]),
+                            }),
+                            ..CodeAction::default()
+                        }));
+
+                        actions
                     } else {
                         tracing::error!(
                             "Deserialization failed: received {:?} as diagnostic data",
@@ -353,16 +592,167 @@ impl LanguageServer for Backend<'static, 'static> {
         Ok(Some(actions))
     }
 
+    async fn execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> jsonrpc::Result<Option<serde_json::Value>> {
+        tracing::debug!(
+            "execute_command: {:?}",
+            to_string(&params).unwrap_or_default()
+        );
+
+        if params.command != ADD_WORD_TO_DICTIONARY_COMMAND {
+            tracing::warn!("execute_command: unknown command {}", params.command);
+            return Ok(None);
+        }
+
+        let (Some(uri), Some(word)) = (
+            params
+                .arguments
+                .first()
+                .and_then(|a| serde_json::from_value::<Url>(a.clone()).ok()),
+            params.arguments.get(1).and_then(|a| a.as_str()),
+        ) else {
+            tracing::warn!(
+                "execute_command: expected [uri, word] arguments, got {:?}",
+                params.arguments
+            );
+            return Ok(None);
+        };
+
+        if let Err(e) = self.add_word_to_dictionary(&uri, word) {
+            tracing::warn!(
+                "execute_command: failed to add `{}` to dictionary: {}",
+                word,
+                e
+            );
+            return Ok(None);
+        }
+
+        self.refresh_diagnostics().await;
+
+        Ok(None)
+    }
+
+    async fn diagnostic(
+        &self,
+        params: DocumentDiagnosticParams,
+    ) -> jsonrpc::Result<DocumentDiagnosticReportResult> {
+        tracing::debug!("diagnostic: {:?}", to_string(&params).unwrap_or_default());
+
+        let uri = params.text_document.uri;
+        let text = self
+            .state
+            .lock()
+            .unwrap()
+            .documents
+            .get(&uri)
+            .cloned()
+            .unwrap_or_default();
+
+        let items = self.check_text(&text, &uri);
+
+        Ok(DocumentDiagnosticReportResult::Report(
+            DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
+                related_documents: None,
+                full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                    result_id: None,
+                    items,
+                },
+            }),
+        ))
+    }
+
+    async fn workspace_diagnostic(
+        &self,
+        params: WorkspaceDiagnosticParams,
+    ) -> jsonrpc::Result<WorkspaceDiagnosticReportResult> {
+        tracing::debug!(
+            "workspace_diagnostic: {:?}",
+            to_string(&params).unwrap_or_default()
+        );
+
+        let documents: Vec<(Url, String)> = {
+            let state = self.state.lock().unwrap();
+            state
+                .documents
+                .iter()
+                .map(|(uri, text)| (uri.clone(), text.clone()))
+                .collect()
+        };
+
+        let items = documents
+            .into_iter()
+            .map(|(uri, text)| {
+                let items = self.check_text(&text, &uri);
+                WorkspaceDocumentDiagnosticReport::Full(WorkspaceFullDocumentDiagnosticReport {
+                    uri,
+                    version: None,
+                    full_document_diagnostic_report: FullDocumentDiagnosticReport {
+                        result_id: None,
+                        items,
+                    },
+                })
+            })
+            .collect();
+
+        Ok(WorkspaceDiagnosticReportResult::Report(
+            WorkspaceDiagnosticReport { items },
+        ))
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        tracing::debug!(
+            "did_change_configuration: {:?}",
+            to_string(&params).unwrap_or_default()
+        );
+
+        {
+            let mut state = self.state.lock().unwrap();
+            state.apply_settings(&params.settings);
+            if let Err(e) = state.update_router() {
+                tracing::warn!("Cannot rebuild router after configuration change: {}", e);
+            }
+        }
+
+        self.refresh_diagnostics().await;
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        tracing::debug!(
+            "did_change_watched_files: {:?}",
+            to_string(&params).unwrap_or_default()
+        );
+
+        // matchit's router has no in-place update, so a config file edit just rebuilds the
+        // whole router (cheap relative to a keystroke) rather than only the affected route
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Err(e) = state.update_router() {
+                tracing::warn!("Cannot rebuild router after config file change: {}", e);
+            }
+        }
+
+        self.refresh_diagnostics().await;
+    }
+
     async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
         tracing::debug!(
             "did_change_workspace_folders: {:?}",
             to_string(&params).unwrap_or_default()
         );
 
-        let mut state = self.state.lock().unwrap();
-        if let Err(e) = state.update_workspace_folders(params.event.added, params.event.removed) {
-            tracing::warn!("Cannot update workspace folders {}", e);
+        {
+            let mut state = self.state.lock().unwrap();
+            if let Err(e) =
+                state.update_workspace_folders(params.event.added, params.event.removed)
+            {
+                tracing::warn!("Cannot update workspace folders {}", e);
+            }
         }
+
+        // the effective policy for open documents may have changed, so re-check them all
+        self.refresh_diagnostics().await;
     }
 
     async fn shutdown(&self) -> jsonrpc::Result<()> {
@@ -386,50 +776,102 @@ impl<'s, 'p> Backend<'s, 'p> {
             .await;
     }
 
-    // mimics typos_cli::file::FileChecker::check_file
-    fn check_text(&self, buffer: &str, uri: &Url) -> Vec<Diagnostic> {
-        let path = uri.to_file_path().unwrap_or_else(|_| {
-            tracing::warn!("check_text: Cannot convert uri {} to file path", uri);
-            PathBuf::default()
-        });
+    // re-checks every open document and republishes diagnostics, then nudges pull-model
+    // clients via workspace/diagnostic/refresh
+    async fn refresh_diagnostics(&self) {
+        let documents: Vec<(Url, String)> = {
+            let state = self.state.lock().unwrap();
+            state
+                .documents
+                .iter()
+                .map(|(uri, text)| (uri.clone(), text.clone()))
+                .collect()
+        };
 
-        let uri_path = url_path_sanitised(uri);
+        for (uri, text) in documents {
+            let diagnostics = self.check_text(&text, &uri);
+            self.client.publish_diagnostics(uri, diagnostics, None).await;
+        }
 
+        if let Err(e) = self.client.workspace_diagnostic_refresh().await {
+            tracing::warn!("Failed to request workspace/diagnostic/refresh: {}", e);
+        }
+    }
+
+    // writes `word` into the config file governing `uri` and rebuilds the router
+    fn add_word_to_dictionary(&self, uri: &Url, word: &str) -> anyhow::Result<()> {
+        let path = uri
+            .to_file_path()
+            .map_err(|_| anyhow!("Cannot convert uri {} to file path", uri))?;
+
+        let mut state = self.state.lock().unwrap();
+        let config_path =
+            resolve_config_path(&path, &state.workspace_folders, state.config.as_deref());
+        add_word_to_config(&config_path, word)?;
+
+        state.update_router()
+    }
+
+    // mimics typos_cli::file::FileChecker::check_file
+    fn check_text(&self, buffer: &str, uri: &Url) -> Vec<Diagnostic> {
         let state = self.state.lock().unwrap();
 
-        // find relevant overrides and engine for the workspace folder
-        let (overrides, tokenizer, dict) = match state.router.at(&uri_path) {
-            Err(_) => {
-                tracing::warn!(
-                    "check_text: Using default policy because no route found for {}",
-                    uri_path
-                );
-                (
-                    None,
-                    self.default_policy.tokenizer,
-                    self.default_policy.dict,
-                )
-            }
-            Ok(Match { value, params: _ }) => {
-                // TODO store policy in router
-                tracing::debug!("check_text: path {}", &path.display());
-                let policy = value.engine.policy(&path);
-                (Some(&value.overrides), policy.tokenizer, policy.dict)
-            }
-        };
+        // find relevant engine for the document, applying extend-exclude/path-based policy
+        // lookup only when the document is backed by a real path on disk
+        let (tokenizer, dict) = match DocumentUri::new(uri) {
+            DocumentUri::File(path) => {
+                let uri_path = url_path_sanitised(uri);
+                match state.router.at(&uri_path) {
+                    Err(_) => {
+                        tracing::warn!(
+                            "check_text: Using default policy because no route found for {}",
+                            uri_path
+                        );
+                        (self.default_policy.tokenizer, self.default_policy.dict)
+                    }
+                    Ok(Match { value, params: _ }) => {
+                        // skip file if matches extend-exclude
+                        if value.overrides.matched(&path, false).is_ignore() {
+                            tracing::debug!(
+                                "check_text: Ignoring {} because it matches extend-exclude.",
+                                uri
+                            );
+                            return Vec::default();
+                        }
 
-        // skip file if matches extend-exclude
-        if let Some(overrides) = overrides {
-            if overrides.matched(path, false).is_ignore() {
+                        // TODO store policy in router
+                        tracing::debug!("check_text: path {}", &path.display());
+                        let policy = value.engine.policy(&path);
+                        (policy.tokenizer, policy.dict)
+                    }
+                }
+            }
+            DocumentUri::InMemory { scheme } => {
+                // not backed by a local path, so there's nothing to match extend-exclude or
+                // the path-based policy lookup against; fall back to the first workspace
+                // folder's policy (or the default policy) so scratch buffers still get checked
                 tracing::debug!(
-                    "check_text: Ignoring {} because it matches extend-exclude.",
-                    uri
+                    "check_text: {} has scheme {}, using first workspace folder policy",
+                    uri,
+                    scheme
                 );
-                return Vec::default();
+                match state.workspace_folders.first().and_then(|folder| {
+                    let root = folder.uri.to_file_path().ok()?;
+                    // the folder's route is `<folder>/*p`, whose catch-all only matches a
+                    // non-empty segment after the folder path
+                    let route_path = format!("{}/{}", url_path_sanitised(&folder.uri), scheme);
+                    state.router.at(&route_path).ok().map(|m| (root, m.value))
+                }) {
+                    Some((root, value)) => {
+                        let policy = value.engine.policy(&root);
+                        (policy.tokenizer, policy.dict)
+                    }
+                    None => (self.default_policy.tokenizer, self.default_policy.dict),
+                }
             }
-        }
+        };
 
-        let mut accum = AccumulatePosition::new();
+        let mut accum = AccumulatePosition::new(state.position_encoding);
 
         typos::check_str(buffer, tokenizer, dict)
             .map(|typo| {
@@ -453,13 +895,14 @@ impl<'s, 'p> Backend<'s, 'p> {
                         ),
                         typos::Status::Valid => panic!("unexpected typos::Status::Valid"),
                     },
-                    // store corrections for retrieval during code_action
-                    data: match typo.corrections {
-                        typos::Status::Corrections(corrections) => {
-                            Some(json!(DiagnosticData { corrections }))
-                        }
-                        _ => None,
-                    },
+                    // store the typo and any corrections for retrieval during code_action
+                    data: Some(json!(DiagnosticData {
+                        corrections: match typo.corrections {
+                            typos::Status::Corrections(corrections) => corrections,
+                            _ => Vec::new(),
+                        },
+                        typo: typo.typo.clone(),
+                    })),
                     ..Diagnostic::default()
                 }
             })
@@ -467,19 +910,77 @@ impl<'s, 'p> Backend<'s, 'p> {
     }
 }
 
+// applies a single incremental content change to `buffer` in place, replacing
+// the whole document when `range` is absent as per the `TextDocumentContentChangeEvent` spec
+fn apply_content_change(
+    buffer: &mut String,
+    change: TextDocumentContentChangeEvent,
+    encoding: PositionEncoding,
+) {
+    match change.range {
+        None => *buffer = change.text,
+        Some(range) => {
+            let start = position_to_byte_offset(buffer.as_bytes(), range.start, encoding);
+            let end = position_to_byte_offset(buffer.as_bytes(), range.end, encoding);
+            buffer.replace_range(start..end, &change.text);
+        }
+    }
+}
+
+// the inverse of AccumulatePosition::pos: maps an LSP line/column position, expressed in
+// the negotiated encoding, back to a byte offset into `buffer`
+fn position_to_byte_offset(
+    buffer: &[u8],
+    position: Position,
+    encoding: PositionEncoding,
+) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match buffer[line_start..].find_byte(b'\n') {
+            Some(idx) => line_start += idx + 1,
+            None => return buffer.len(),
+        }
+    }
+
+    let line_end = buffer[line_start..]
+        .find_byte(b'\n')
+        .map(|idx| line_start + idx)
+        .unwrap_or(buffer.len());
+
+    if encoding == PositionEncoding::Utf8 {
+        return (line_start + position.character as usize).min(line_end);
+    }
+
+    let line = String::from_utf8_lossy(&buffer[line_start..line_end]);
+    let mut col_pos = 0u32;
+    for (byte_idx, ch) in line.char_indices() {
+        if col_pos >= position.character {
+            return line_start + byte_idx;
+        }
+        col_pos += match encoding {
+            PositionEncoding::Utf8 => unreachable!(),
+            PositionEncoding::Utf16 => ch.len_utf16() as u32,
+            PositionEncoding::Utf32 => 1,
+        };
+    }
+    line_end
+}
+
 struct AccumulatePosition {
     line_num: usize,
     line_pos: usize,
     last_offset: usize,
+    encoding: PositionEncoding,
 }
 
 impl AccumulatePosition {
-    fn new() -> Self {
+    fn new(encoding: PositionEncoding) -> Self {
         Self {
             // LSP ranges are 0-indexed see https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#range
             line_num: 0,
             line_pos: 0,
             last_offset: 0,
+            encoding,
         }
     }
 
@@ -495,12 +996,19 @@ impl AccumulatePosition {
             .map(|s| s + 1)
             .unwrap_or(0);
 
-        let before_typo = String::from_utf8_lossy(&buffer[line_start..byte_offset]);
-
-        // count UTF-16 code units as per
+        // column is expressed in whatever encoding was negotiated during initialize, see
         // https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#textDocuments
-        // UTF-16 is the only position encoding we support for now
-        let line_pos = before_typo.chars().map(char::len_utf16).sum();
+        let line_pos = match self.encoding {
+            PositionEncoding::Utf8 => byte_offset - line_start,
+            PositionEncoding::Utf16 => {
+                let before_typo = String::from_utf8_lossy(&buffer[line_start..byte_offset]);
+                before_typo.chars().map(char::len_utf16).sum()
+            }
+            PositionEncoding::Utf32 => {
+                let before_typo = String::from_utf8_lossy(&buffer[line_start..byte_offset]);
+                before_typo.chars().count()
+            }
+        };
 
         self.line_num = line_num;
         self.line_pos = line_pos;